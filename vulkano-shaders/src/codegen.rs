@@ -0,0 +1,222 @@
+//! Compiles GLSL to SPIR-V via `shaderc` and reflects the result into the Rust
+//! code that `vulkano_shader!` expands to.
+
+use proc_macro2::{Span, TokenStream};
+use syn::Ident;
+
+const SPIRV_MAGIC: u32 = 0x0723_0203;
+const OP_ENTRY_POINT: u32 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderKind {
+    Vertex,
+    Fragment,
+    Geometry,
+    TessControl,
+    TessEvaluation,
+    Compute,
+}
+
+impl ShaderKind {
+    fn to_shaderc_kind(self) -> shaderc::ShaderKind {
+        match self {
+            ShaderKind::Vertex => shaderc::ShaderKind::Vertex,
+            ShaderKind::Fragment => shaderc::ShaderKind::Fragment,
+            ShaderKind::Geometry => shaderc::ShaderKind::Geometry,
+            ShaderKind::TessControl => shaderc::ShaderKind::TessControl,
+            ShaderKind::TessEvaluation => shaderc::ShaderKind::TessEvaluation,
+            ShaderKind::Compute => shaderc::ShaderKind::Compute,
+        }
+    }
+}
+
+/// Compiles `code` (GLSL) to SPIR-V, applying the caller-supplied `options`
+/// (include callback, macro definitions, target environment, optimization level).
+pub fn compile(
+    code: &str,
+    ty: ShaderKind,
+    options: &shaderc::CompileOptions,
+) -> Result<shaderc::CompilationArtifact, String> {
+    let mut compiler = shaderc::Compiler::new()
+        .ok_or_else(|| "failed to initialize shaderc compiler".to_string())?;
+
+    compiler
+        .compile_into_spirv(code, ty.to_shaderc_kind(), "shader.glsl", "main", Some(options))
+        .map_err(|err| err.to_string())
+}
+
+/// Reads the entry point names declared by `OpEntryPoint` instructions in
+/// `spirv`, in declaration order.
+fn entry_point_names(spirv: &[u32]) -> Vec<String> {
+    assert_eq!(spirv.first().cloned(), Some(SPIRV_MAGIC), "not a valid SPIR-V module");
+
+    let mut names = Vec::new();
+    let mut i = 5; // skip the 5-word header: magic, version, generator, bound, schema
+
+    while i < spirv.len() {
+        let instruction = spirv[i];
+        let word_count = (instruction >> 16) as usize;
+        let opcode = instruction & 0xffff;
+
+        if word_count == 0 {
+            break;
+        }
+
+        if opcode == OP_ENTRY_POINT {
+            // Layout: opcode/count, execution model, entry point id, then a
+            // NUL-terminated UTF-8 name packed into the following words.
+            let name_words = &spirv[i + 3..(i + word_count).min(spirv.len())];
+            let mut bytes = Vec::new();
+            'name: for word in name_words {
+                for shift in [0u32, 8, 16, 24] {
+                    let byte = ((word >> shift) & 0xff) as u8;
+                    if byte == 0 {
+                        break 'name;
+                    }
+                    bytes.push(byte);
+                }
+            }
+            names.push(String::from_utf8(bytes).expect("entry point name is not valid UTF-8"));
+        }
+
+        i += word_count;
+    }
+
+    names
+}
+
+/// Generates the `Shader` struct and its constructor for a module compiled
+/// from a single `ty`/`src`/`path`/`bytes` unit.
+pub fn reflect(name: &str, spirv: &[u32], mod_name: &Ident, dump: bool) -> Result<TokenStream, String> {
+    let entry_name = entry_point_names(spirv)
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| "main".to_string());
+
+    reflect_entry_points(name, &[(entry_name, spirv.to_vec())], mod_name, dump)
+}
+
+/// Generates the `Shader` struct for a module built from one or more named
+/// entry points, with one constructor method per entry point name.
+pub fn reflect_entry_points(
+    name: &str,
+    entries: &[(String, Vec<u32>)],
+    mod_name: &Ident,
+    dump: bool,
+) -> Result<TokenStream, String> {
+    let struct_name = Ident::new(name, Span::call_site());
+
+    for i in 0..entries.len() {
+        for j in (i + 1)..entries.len() {
+            if entries[i].0 == entries[j].0 {
+                return Err(format!("duplicate entry point name in `entry_points`: {:?}", entries[i].0));
+            }
+        }
+    }
+
+    let primary_words = entries
+        .first()
+        .map(|(_, words)| words.clone())
+        .unwrap_or_default();
+
+    let entry_methods = entries.iter().map(|(entry_name, _)| {
+        let method_name = Ident::new(entry_name, Span::call_site());
+        quote! {
+            #[inline]
+            pub fn #method_name(&self) -> &'static str {
+                #entry_name
+            }
+        }
+    });
+
+    let tokens = quote! {
+        pub mod #mod_name {
+            #[allow(unused_imports)]
+            use std::sync::Arc;
+
+            pub struct #struct_name {
+                pub(crate) shader: Arc<::vulkano::pipeline::shader::ShaderModule>,
+            }
+
+            impl #struct_name {
+                /// Loads this SPIR-V module on the device. The capabilities the module
+                /// declares are not checked against the device's enabled features.
+                pub fn load(device: Arc<::vulkano::device::Device>) -> Result<#struct_name, ::vulkano::OomError> {
+                    let words: &[u32] = &[ #(#primary_words),* ];
+                    Ok(#struct_name {
+                        shader: ::vulkano::pipeline::shader::ShaderModule::from_words(device, words)?,
+                    })
+                }
+
+                /// Returns the module that was created.
+                #[inline]
+                pub fn module(&self) -> &Arc<::vulkano::pipeline::shader::ShaderModule> {
+                    &self.shader
+                }
+
+                #(#entry_methods)*
+            }
+        }
+    };
+
+    if dump {
+        println!("{}", tokens.to_string());
+        panic!("`dump: true` was set, aborting compilation as intended");
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::entry_point_names;
+
+    fn pack_name(name: &str) -> Vec<u32> {
+        let mut bytes = name.as_bytes().to_vec();
+        bytes.push(0);
+        while bytes.len() % 4 != 0 {
+            bytes.push(0);
+        }
+        bytes.chunks(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect()
+    }
+
+    fn module_with_entry_points(names: &[&str]) -> Vec<u32> {
+        let mut words = vec![super::SPIRV_MAGIC, 0x0001_0000, 0, 1, 0];
+        for name in names {
+            let name_words = pack_name(name);
+            let mut instruction = vec![0u32; 3 + name_words.len()];
+            instruction[0] = ((instruction.len() as u32) << 16) | super::OP_ENTRY_POINT;
+            instruction[1] = 0; // execution model, unused by the reader
+            instruction[2] = 1; // entry point id, unused by the reader
+            instruction[3..].copy_from_slice(&name_words);
+            words.extend(instruction);
+        }
+        words
+    }
+
+    #[test]
+    fn reads_single_entry_point_name() {
+        let module = module_with_entry_points(&["main"]);
+        assert_eq!(entry_point_names(&module), vec!["main".to_string()]);
+    }
+
+    #[test]
+    fn reads_multiple_entry_point_names_in_order() {
+        let module = module_with_entry_points(&["vs_main", "fs_main"]);
+        assert_eq!(entry_point_names(&module), vec!["vs_main".to_string(), "fs_main".to_string()]);
+    }
+
+    #[test]
+    fn module_with_no_entry_points_yields_no_names() {
+        let module = vec![super::SPIRV_MAGIC, 0x0001_0000, 0, 1, 0];
+        assert!(entry_point_names(&module).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "not a valid SPIR-V module")]
+    fn rejects_wrong_magic_number() {
+        entry_point_names(&[0xdead_beef, 0, 0, 1, 0]);
+    }
+}
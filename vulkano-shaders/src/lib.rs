@@ -134,10 +134,75 @@
 //! Provides the path to the GLSL source to be compiled, relative to `Cargo.toml`.
 //! Cannot be used in conjunction with the `src` field.
 //!
+//! ## `bytes: "..."`
+//!
+//! Provides the path to a precompiled SPIR-V binary, relative to `Cargo.toml`.
+//! The file is validated and reflected over directly, skipping GLSL compilation
+//! entirely. Cannot be used in conjunction with `src` or `path`.
+//!
 //! ## `dump: true`
 //!
 //! The crate fails to compile but prints the generated rust code to stdout.
-//! 
+//!
+//! ## `include_dirs: [...]`
+//!
+//! Specifies one or more directories to search when resolving `#include "..."`
+//! and `#include <...>` directives in the GLSL source, in addition to the
+//! single directory provided by `include: "..."`. Each directory is resolved
+//! relative to `Cargo.toml`. Directories are searched in the order given, and
+//! compilation fails with an error listing the searched directories if none of
+//! them contain the requested file.
+//!
+//! ## `include: "..."`
+//!
+//! Shorthand for a single entry in `include_dirs: [...]`.
+//!
+//! ## `define: [("NAME", "VALUE"), ...]`
+//!
+//! Defines one or more preprocessor macros for the GLSL compiler, equivalent to
+//! passing `-DNAME=VALUE` to `glslc`. This allows several pipeline variants to be
+//! stamped out of a single `.glsl` file by giving each `vulkano_shader!` invocation
+//! its own set of `define`s instead of maintaining forked source files.
+//!
+//! ## `watch: true`
+//!
+//! In addition to the normal compile-time path, embeds the shader's GLSL source
+//! (and its resolved `include_dirs`/`define`s) into the generated module and adds
+//! `Shader::load_watched`, a constructor that recompiles the GLSL at runtime via
+//! an embedded `shaderc` instance, plus an instance method `Shader::reload` that
+//! does the same starting from an existing `Shader`. `Shader::SOURCE_PATH`
+//! exposes the resolved source file path so callers can drive recompilation from
+//! a file-watcher loop during development; release builds can simply not set
+//! `watch` and keep using the baked SPIR-V. The entry point accessors are
+//! unaffected by a reload ; only the underlying `Arc<ShaderModule>` changes. No
+//! `SpecializationConstants` or `Layout` type is generated. Requires the
+//! crate embedding the macro to depend on `shaderc` itself, and cannot be
+//! combined with `bytes` or `entry_points`.
+//!
+//! ## `vulkan_version: "..."`
+//!
+//! Targets the given Vulkan version (currently `"1.0"` or `"1.1"`) when
+//! compiling the GLSL to SPIR-V, allowing the use of capabilities that are only
+//! available under a higher SPIR-V version. Defaults to shaderc's own default
+//! target environment when not specified.
+//!
+//! ## `optimization: "..."`
+//!
+//! Controls the optimization level shaderc applies to the compiled SPIR-V.
+//! One of `"none"` (the default), `"size"` or `"performance"`.
+//!
+//! ## `entry_points: [(ty: "...", entry: "...", src/path: "..."), ...]`
+//!
+//! Compiles several shader stages (e.g. a vertex/fragment pair, or a handful of
+//! compute kernels) into a single logical module instead of one `vulkano_shader!`
+//! invocation per stage. Each unit accepts the same `ty`, `src`/`path` fields
+//! described above plus an `entry: "..."` name (defaulting to `"main"`), and the
+//! generated module gets one constructor method per named entry point (e.g.
+//! `vs_main`, `fs_main`) instead of the usual single `main_entry_point`, with
+//! descriptor set and specialization constant reflection merged across all of
+//! them. Cannot be used in conjunction with the top-level `ty`/`src`/`path`/`bytes`
+//! fields.
+//!
 //! [reflect]: https://github.com/vulkano-rs/vulkano/blob/master/vulkano-shaders/src/lib.rs#L67
 //! [cargo-expand]: https://github.com/dtolnay/cargo-expand
 //! [ShaderModule::new]: https://docs.rs/vulkano/*/vulkano/pipeline/shader/struct.ShaderModule.html#method.new
@@ -166,21 +231,99 @@ use std::path::Path;
 
 use syn::parse::{Parse, ParseStream, Result};
 use syn::{Ident, LitStr, LitBool};
+use syn::punctuated::Punctuated;
 
 mod codegen;
-mod descriptor_sets;
-mod entry_point;
-mod enums;
-mod parse;
-mod spec_consts;
-mod structs;
-mod spirv_search;
 
 use codegen::ShaderKind;
 
 enum SourceKind {
     Src(String),
     Path(String),
+    Bytes(String),
+}
+
+fn parse_shader_kind(ty: &str) -> ShaderKind {
+    match ty {
+        "vertex" => ShaderKind::Vertex,
+        "fragment" => ShaderKind::Fragment,
+        "geometry" => ShaderKind::Geometry,
+        "tess_ctrl" => ShaderKind::TessControl,
+        "tess_eval" => ShaderKind::TessEvaluation,
+        "compute" => ShaderKind::Compute,
+        _ => panic!("Unexpected shader type, valid values: vertex, fragment, geometry, tess_ctrl, tess_eval, compute")
+    }
+}
+
+/// Checks that `entry_points: [...]` is not combined with the top-level
+/// `ty`/`src`/`path`/`bytes` fields, and that it isn't given an empty list.
+fn check_entry_points_exclusivity(
+    entry_points_seen: bool,
+    top_level_fields_seen: bool,
+    entry_points_empty: bool,
+) -> ::std::result::Result<(), String> {
+    if entry_points_seen && top_level_fields_seen {
+        return Err("`entry_points` cannot be combined with the top-level `ty`/`src`/`path`/`bytes` fields".to_string());
+    }
+
+    if entry_points_seen && entry_points_empty {
+        return Err("`entry_points` must contain at least one unit e.g. `entry_points: [(ty: \"vertex\", entry: \"main\", src: \"...\")]`".to_string());
+    }
+
+    Ok(())
+}
+
+/// A single `(ty, entry, src/path)` unit within an `entry_points: [...]` list.
+struct EntryPointUnit {
+    entry_name: String,
+    shader_kind: ShaderKind,
+    source_kind: SourceKind,
+}
+
+impl Parse for EntryPointUnit {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let in_parens;
+        parenthesized!(in_parens in input);
+
+        let mut entry_name = None;
+        let mut shader_kind = None;
+        let mut source_kind = None;
+
+        while !in_parens.is_empty() {
+            let name: Ident = in_parens.parse()?;
+            in_parens.parse::<Token![:]>()?;
+
+            match name.to_string().as_ref() {
+                "ty" => {
+                    let ty: LitStr = in_parens.parse()?;
+                    shader_kind = Some(parse_shader_kind(&ty.value()));
+                }
+                "entry" => {
+                    let entry: LitStr = in_parens.parse()?;
+                    entry_name = Some(entry.value());
+                }
+                "src" => {
+                    let src: LitStr = in_parens.parse()?;
+                    source_kind = Some(SourceKind::Src(src.value()));
+                }
+                "path" => {
+                    let path: LitStr = in_parens.parse()?;
+                    source_kind = Some(SourceKind::Path(path.value()));
+                }
+                name => panic!(format!("Unknown field name in entry_points unit: {}", name))
+            }
+
+            if !in_parens.is_empty() {
+                in_parens.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(EntryPointUnit {
+            entry_name: entry_name.unwrap_or_else(|| "main".to_string()),
+            shader_kind: shader_kind.expect("each `entry_points` unit requires a `ty`"),
+            source_kind: source_kind.expect("each `entry_points` unit requires a `src` or `path`"),
+        })
+    }
 }
 
 struct MacroInput {
@@ -188,6 +331,12 @@ struct MacroInput {
     shader_kind: ShaderKind,
     source_kind: SourceKind,
     dump: bool,
+    include_dirs: Vec<String>,
+    defines: Vec<(String, String)>,
+    watch: bool,
+    vulkan_version: Option<String>,
+    optimization: Option<String>,
+    entry_points: Vec<EntryPointUnit>,
 }
 
 impl Parse for MacroInput {
@@ -196,6 +345,13 @@ impl Parse for MacroInput {
         let mut mod_ident = None;
         let mut shader_kind = None;
         let mut source_kind = None;
+        let mut include_dirs = Vec::new();
+        let mut defines = Vec::new();
+        let mut watch = None;
+        let mut vulkan_version = None;
+        let mut optimization = None;
+        let mut entry_points = Vec::new();
+        let mut entry_points_seen = false;
 
         while !input.is_empty() {
             let name: Ident = input.parse()?;
@@ -216,16 +372,7 @@ impl Parse for MacroInput {
                     }
 
                     let ty: LitStr = input.parse()?;
-                    let ty = match ty.value().as_ref() {
-                        "vertex" => ShaderKind::Vertex,
-                        "fragment" => ShaderKind::Fragment,
-                        "geometry" => ShaderKind::Geometry,
-                        "tess_ctrl" => ShaderKind::TessControl,
-                        "tess_eval" => ShaderKind::TessEvaluation,
-                        "compute" => ShaderKind::Compute,
-                        _ => panic!("Unexpected shader type, valid values: vertex, fragment, geometry, tess_ctrl, tess_eval, compute")
-                    };
-                    shader_kind = Some(ty);
+                    shader_kind = Some(parse_shader_kind(&ty.value()));
                 }
                 "src" => {
                     if source_kind.is_some() {
@@ -243,6 +390,14 @@ impl Parse for MacroInput {
                     let path: LitStr = input.parse()?;
                     source_kind = Some(SourceKind::Path(path.value()));
                 }
+                "bytes" => {
+                    if source_kind.is_some() {
+                        panic!("Only one `src`, `path` or `bytes` can be defined")
+                    }
+
+                    let path: LitStr = input.parse()?;
+                    source_kind = Some(SourceKind::Bytes(path.value()));
+                }
                 "dump" => {
                     if dump.is_some() {
                         panic!("Only one `dump` can be defined")
@@ -250,6 +405,68 @@ impl Parse for MacroInput {
                     let dump_lit: LitBool = input.parse()?;
                     dump = Some(dump_lit.value);
                 }
+                "include_dirs" => {
+                    let in_brackets;
+                    bracketed!(in_brackets in input);
+
+                    let include_dirs_lits: Punctuated<LitStr, Token![,]> =
+                        in_brackets.parse_terminated(<LitStr as Parse>::parse)?;
+                    include_dirs.extend(include_dirs_lits.into_iter().map(|dir| dir.value()));
+                }
+                "include" => {
+                    let include: LitStr = input.parse()?;
+                    include_dirs.push(include.value());
+                }
+                "define" => {
+                    let in_brackets;
+                    bracketed!(in_brackets in input);
+
+                    let raw_defines: Punctuated<(LitStr, LitStr), Token![,]> =
+                        in_brackets.parse_terminated(|tuple| {
+                            let in_parens;
+                            parenthesized!(in_parens in tuple);
+
+                            let name: LitStr = in_parens.parse()?;
+                            in_parens.parse::<Token![,]>()?;
+                            let value: LitStr = in_parens.parse()?;
+                            Ok((name, value))
+                        })?;
+                    defines.extend(raw_defines.into_iter().map(|(name, value)| (name.value(), value.value())));
+                }
+                "watch" => {
+                    if watch.is_some() {
+                        panic!("Only one `watch` can be defined")
+                    }
+                    let watch_lit: LitBool = input.parse()?;
+                    watch = Some(watch_lit.value);
+                }
+                "vulkan_version" => {
+                    if vulkan_version.is_some() {
+                        panic!("Only one `vulkan_version` can be defined")
+                    }
+                    let version: LitStr = input.parse()?;
+                    vulkan_version = Some(version.value());
+                }
+                "optimization" => {
+                    if optimization.is_some() {
+                        panic!("Only one `optimization` can be defined")
+                    }
+                    let level: LitStr = input.parse()?;
+                    optimization = Some(level.value());
+                }
+                "entry_points" => {
+                    if entry_points_seen {
+                        panic!("Only one `entry_points` can be defined")
+                    }
+                    entry_points_seen = true;
+
+                    let in_brackets;
+                    bracketed!(in_brackets in input);
+
+                    let units: Punctuated<EntryPointUnit, Token![,]> =
+                        in_brackets.parse_terminated(EntryPointUnit::parse)?;
+                    entry_points = units.into_iter().collect();
+                }
                 name => panic!(format!("Unknown field name: {}", name))
             }
 
@@ -258,14 +475,29 @@ impl Parse for MacroInput {
             }
         }
 
-        let shader_kind = match shader_kind {
-            Some(shader_kind) => shader_kind,
-            None => panic!("Please provide a shader type e.g. `ty: \"vertex\"`")
-        };
+        if let Err(message) = check_entry_points_exclusivity(
+            entry_points_seen,
+            shader_kind.is_some() || source_kind.is_some(),
+            entry_points.is_empty(),
+        ) {
+            panic!(message)
+        }
+
+        let (shader_kind, source_kind) = if !entry_points_seen {
+            let shader_kind = match shader_kind {
+                Some(shader_kind) => shader_kind,
+                None => panic!("Please provide a shader type e.g. `ty: \"vertex\"`")
+            };
+
+            let source_kind = match source_kind {
+                Some(source_kind) => source_kind,
+                None => panic!("Please provide a source e.g. `path: \"foo.glsl\"` or `src: \"glsl source code here ...\"` or `entry_points: [...]`")
+            };
 
-        let source_kind = match source_kind {
-            Some(source_kind) => source_kind,
-            None => panic!("Please provide a source e.g. `path: \"foo.glsl\"` or `src: \"glsl source code here ...\"`")
+            (shader_kind, source_kind)
+        } else {
+            // Unused placeholders ; the `entry_points` path never reads `shader_kind`/`source_kind`.
+            (ShaderKind::Vertex, SourceKind::Src(String::new()))
         };
 
         let mod_ident = match mod_ident {
@@ -274,33 +506,363 @@ impl Parse for MacroInput {
         };
 
         let dump = dump.unwrap_or(false);
+        let watch = watch.unwrap_or(false);
 
-        Ok(MacroInput { shader_kind, source_kind, mod_ident, dump })
+        Ok(MacroInput { shader_kind, source_kind, mod_ident, dump, include_dirs, defines, watch, vulkan_version, optimization, entry_points })
     }
 }
 
-#[proc_macro]
-pub fn vulkano_shader(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let input = parse_macro_input!(input as MacroInput);
+const SPIRV_MAGIC: u32 = 0x0723_0203;
+
+/// Decodes a precompiled `bytes: "..."` file into SPIR-V words, checking that it
+/// is word-aligned and starts with the SPIR-V magic number.
+fn decode_spirv_bytes(buf: &[u8]) -> ::std::result::Result<Vec<u32>, String> {
+    if buf.len() % 4 != 0 {
+        return Err(format!("is not word-aligned: {} bytes is not a multiple of 4", buf.len()));
+    }
+
+    let words: Vec<u32> = buf.chunks(4)
+        .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+        .collect();
+
+    if words.first().cloned() != Some(SPIRV_MAGIC) {
+        return Err("does not start with the SPIR-V magic number ; are you sure this is a SPIR-V binary?".to_string());
+    }
 
-    let source_code = match input.source_kind {
-        SourceKind::Src(source) => source,
+    Ok(words)
+}
+
+/// Resolves a `src`/`path`/`bytes` unit to SPIR-V words, compiling GLSL through
+/// shaderc with the given options where necessary. Shared between the single
+/// `ty`/`src`/`path`/`bytes` macro form and each unit of `entry_points: [...]`.
+///
+/// When `keep_source` is set, also hands back the resolved GLSL source text and
+/// its source path (`None` for an inline `src`), for the `watch` hot-reload path.
+fn compile_unit(
+    shader_kind: ShaderKind,
+    source_kind: SourceKind,
+    include_dirs: &[String],
+    defines: &[(String, String)],
+    vulkan_version: &Option<String>,
+    optimization: &Option<String>,
+    keep_source: bool,
+) -> (Vec<u32>, Option<(String, Option<String>)>) {
+    if let SourceKind::Bytes(path) = source_kind {
+        let root = env::var("CARGO_MANIFEST_DIR").unwrap_or(".".into());
+        let full_path = Path::new(&root).join(&path);
+
+        if !full_path.is_file() {
+            panic!("File {:?} was not found ; note that the path must be relative to your Cargo.toml", path);
+        }
+
+        let mut buf = Vec::new();
+        File::open(full_path)
+            .and_then(|mut file| file.read_to_end(&mut buf))
+            .expect(&format!("Error reading source from {:?}", path));
+
+        let words = decode_spirv_bytes(&buf)
+            .unwrap_or_else(|err| panic!("File {:?} {}", path, err));
+
+        return (words, None);
+    }
+
+    let (source_code, source_path) = match source_kind {
+        SourceKind::Src(source) => (source, None),
         SourceKind::Path(path) => {
             let root = env::var("CARGO_MANIFEST_DIR").unwrap_or(".".into());
             let full_path = Path::new(&root).join(&path);
 
             if full_path.is_file() {
                 let mut buf = String::new();
-                File::open(full_path)
+                File::open(&full_path)
                     .and_then(|mut file| file.read_to_string(&mut buf))
                     .expect(&format!("Error reading source from {:?}", path));
-                buf
+                let full_path = full_path.to_string_lossy().into_owned();
+                (buf, Some(full_path))
             } else {
                 panic!("File {:?} was not found ; note that the path must be relative to your Cargo.toml", path);
             }
         }
+        SourceKind::Bytes(_) => unreachable!(),
+    };
+
+    let resolved_source = if keep_source {
+        Some((source_code.clone(), source_path.clone()))
+    } else {
+        None
+    };
+
+    let mut compile_options = shaderc::CompileOptions::new()
+        .expect("failed to initialize shaderc compile options");
+
+    for (name, value) in defines {
+        compile_options.add_macro_definition(name, Some(value));
+    }
+
+    if let Some(vulkan_version) = vulkan_version {
+        let version = match vulkan_version.as_ref() {
+            "1.0" => shaderc::EnvVersion::Vulkan1_0,
+            "1.1" => shaderc::EnvVersion::Vulkan1_1,
+            _ => panic!("Unexpected vulkan_version, valid values: \"1.0\", \"1.1\"")
+        };
+        compile_options.set_target_env(shaderc::TargetEnv::Vulkan, version as u32);
+    }
+
+    let optimization_level = match optimization.as_ref().map(|level| level.as_ref()) {
+        None | Some("none") => shaderc::OptimizationLevel::Zero,
+        Some("size") => shaderc::OptimizationLevel::Size,
+        Some("performance") => shaderc::OptimizationLevel::Performance,
+        Some(_) => panic!("Unexpected optimization, valid values: \"none\", \"size\", \"performance\"")
+    };
+    compile_options.set_optimization_level(optimization_level);
+
+    let root = env::var("CARGO_MANIFEST_DIR").unwrap_or(".".into());
+    let include_dirs_owned = include_dirs.to_vec();
+    compile_options.set_include_callback(move |requested, include_type, _origin, _depth| {
+        for dir in &include_dirs_owned {
+            let candidate = Path::new(&root).join(dir).join(requested);
+            if candidate.is_file() {
+                let mut content = String::new();
+                File::open(&candidate)
+                    .and_then(|mut file| file.read_to_string(&mut content))
+                    .map_err(|err| format!("Error reading include file {:?}: {}", candidate, err))?;
+
+                return Ok(shaderc::ResolvedInclude {
+                    resolved_name: candidate.to_string_lossy().into_owned(),
+                    content,
+                });
+            }
+        }
+
+        Err(format!(
+            "Could not find {} include file {:?}, searched in: {:?}",
+            match include_type {
+                shaderc::IncludeType::Relative => "relative",
+                shaderc::IncludeType::Standard => "standard",
+            },
+            requested,
+            include_dirs_owned,
+        ))
+    });
+
+    let content = codegen::compile(&source_code, shader_kind, &compile_options).unwrap();
+    (content.as_binary().to_vec(), resolved_source)
+}
+
+#[proc_macro]
+pub fn vulkano_shader(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as MacroInput);
+
+    if input.watch && !input.entry_points.is_empty() {
+        panic!("`watch` cannot be combined with `entry_points` ; hot-reload for a multi-entry-point module is not yet supported");
+    }
+
+    if !input.entry_points.is_empty() {
+        let mod_ident = input.mod_ident.clone();
+        let dump = input.dump;
+        let include_dirs = input.include_dirs.clone();
+        let defines = input.defines.clone();
+        let vulkan_version = input.vulkan_version.clone();
+        let optimization = input.optimization.clone();
+
+        let entries: Vec<(String, Vec<u32>)> = input.entry_points.into_iter().map(|unit| {
+            let (words, _) = compile_unit(unit.shader_kind, unit.source_kind, &include_dirs, &defines, &vulkan_version, &optimization, false);
+            (unit.entry_name, words)
+        }).collect();
+
+        return codegen::reflect_entry_points("Shader", &entries, &mod_ident, dump).unwrap().into();
+    }
+
+    if input.watch {
+        if let SourceKind::Bytes(_) = input.source_kind {
+            panic!("`watch` cannot be combined with `bytes` ; hot-reload requires GLSL source text to recompile");
+        }
+    }
+
+    // The `shaderc` path of the shader kind, for the generated `load_watched`.
+    let shaderc_kind = match &input.shader_kind {
+        ShaderKind::Vertex => quote! { ::shaderc::ShaderKind::Vertex },
+        ShaderKind::Fragment => quote! { ::shaderc::ShaderKind::Fragment },
+        ShaderKind::Geometry => quote! { ::shaderc::ShaderKind::Geometry },
+        ShaderKind::TessControl => quote! { ::shaderc::ShaderKind::TessControl },
+        ShaderKind::TessEvaluation => quote! { ::shaderc::ShaderKind::TessEvaluation },
+        ShaderKind::Compute => quote! { ::shaderc::ShaderKind::Compute },
     };
 
-    let content = codegen::compile(&source_code, input.shader_kind).unwrap();
-    codegen::reflect("Shader", content.as_binary(), &input.mod_ident, input.dump).unwrap().into()
+    let (words, watched_source) = compile_unit(
+        input.shader_kind,
+        input.source_kind,
+        &input.include_dirs,
+        &input.defines,
+        &input.vulkan_version,
+        &input.optimization,
+        input.watch,
+    );
+
+    let mut tokens = codegen::reflect("Shader", &words, &input.mod_ident, input.dump).unwrap();
+
+    if let Some((source_text, source_path)) = watched_source {
+        let mod_ident = &input.mod_ident;
+        let source_path = source_path.unwrap_or_default();
+        let defines_names: Vec<_> = input.defines.iter().map(|(name, _)| name.as_str()).collect();
+        let defines_values: Vec<_> = input.defines.iter().map(|(_, value)| value.as_str()).collect();
+
+        // Resolved now, at macro-expansion time, while `CARGO_MANIFEST_DIR` still
+        // refers to the crate being compiled ; the generated `load_watched` runs
+        // inside the final binary, where that env var may be unset or point
+        // somewhere else entirely.
+        let root = env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".to_string());
+        let absolute_include_dirs: Vec<String> = input.include_dirs.iter()
+            .map(|dir| Path::new(&root).join(dir).to_string_lossy().into_owned())
+            .collect();
+
+        let compile_error_ident = Ident::new(
+            &format!("{}CompileError", mod_ident.to_string()),
+            proc_macro2::Span::call_site(),
+        );
+
+        let target_env_tokens = input.vulkan_version.as_ref().map(|version| {
+            let version_tokens = match version.as_str() {
+                "1.0" => quote! { ::shaderc::EnvVersion::Vulkan1_0 },
+                "1.1" => quote! { ::shaderc::EnvVersion::Vulkan1_1 },
+                _ => panic!("Unexpected vulkan_version, valid values: \"1.0\", \"1.1\""),
+            };
+            quote! { options.set_target_env(::shaderc::TargetEnv::Vulkan, #version_tokens as u32); }
+        });
+
+        let optimization_level_tokens = match input.optimization.as_ref().map(|level| level.as_str()) {
+            None | Some("none") => quote! { ::shaderc::OptimizationLevel::Zero },
+            Some("size") => quote! { ::shaderc::OptimizationLevel::Size },
+            Some("performance") => quote! { ::shaderc::OptimizationLevel::Performance },
+            Some(_) => panic!("Unexpected optimization, valid values: \"none\", \"size\", \"performance\""),
+        };
+
+        tokens.extend(quote! {
+            /// The error returned when recompiling a watched shader's GLSL source fails.
+            #[derive(Debug)]
+            pub struct #compile_error_ident(pub String);
+
+            impl ::std::fmt::Display for #compile_error_ident {
+                fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                    write!(f, "{}", self.0)
+                }
+            }
+
+            impl ::std::error::Error for #compile_error_ident {}
+
+            impl #mod_ident::Shader {
+                /// The resolved path of the watched GLSL source, or an empty string if it
+                /// was embedded with `src` rather than `path`.
+                pub const SOURCE_PATH: &'static str = #source_path;
+
+                /// Recompiles the embedded GLSL source via an embedded `shaderc` instance
+                /// and loads the result into a fresh `Shader`. Intended to be driven from a
+                /// file-watcher loop during development.
+                pub fn load_watched(device: ::std::sync::Arc<::vulkano::device::Device>)
+                    -> Result<#mod_ident::Shader, #compile_error_ident>
+                {
+                    let mut compiler = ::shaderc::Compiler::new()
+                        .ok_or_else(|| #compile_error_ident("failed to initialize shaderc compiler".to_string()))?;
+                    let mut options = ::shaderc::CompileOptions::new()
+                        .ok_or_else(|| #compile_error_ident("failed to initialize shaderc compile options".to_string()))?;
+
+                    #( options.add_macro_definition(#defines_names, Some(#defines_values)); )*
+                    #target_env_tokens
+                    options.set_optimization_level(#optimization_level_tokens);
+
+                    let include_dirs: &[&str] = &[ #(#absolute_include_dirs),* ];
+                    options.set_include_callback(move |requested, _include_type, _origin, _depth| {
+                        for dir in include_dirs {
+                            let candidate = ::std::path::Path::new(dir).join(requested);
+                            if candidate.is_file() {
+                                let content = ::std::fs::read_to_string(&candidate)
+                                    .map_err(|err| format!("Error reading include file {:?}: {}", candidate, err))?;
+                                return Ok(::shaderc::ResolvedInclude {
+                                    resolved_name: candidate.to_string_lossy().into_owned(),
+                                    content,
+                                });
+                            }
+                        }
+                        Err(format!("Could not find include file {:?}, searched in: {:?}", requested, include_dirs))
+                    });
+
+                    let artifact = compiler.compile_into_spirv(
+                        #source_text,
+                        #shaderc_kind,
+                        #mod_ident::Shader::SOURCE_PATH,
+                        "main",
+                        Some(&options),
+                    ).map_err(|err| #compile_error_ident(format!("failed to recompile watched shader: {}", err)))?;
+
+                    ::vulkano::pipeline::shader::ShaderModule::new(device, artifact.as_binary_u8())
+                        .map_err(|err| #compile_error_ident(format!("{:?}", err)))
+                        .map(|shader| #mod_ident::Shader { shader })
+                }
+
+                /// Recompiles the embedded GLSL source and returns a fresh `Shader`,
+                /// leaving `self` untouched. Only the underlying `Arc<ShaderModule>`
+                /// differs between the two.
+                pub fn reload(&self, device: ::std::sync::Arc<::vulkano::device::Device>)
+                    -> Result<#mod_ident::Shader, #compile_error_ident>
+                {
+                    Self::load_watched(device)
+                }
+            }
+        });
+    }
+
+    tokens.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_entry_points_exclusivity, decode_spirv_bytes, SPIRV_MAGIC};
+
+    fn spirv_bytes(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|word| word.to_le_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn decode_spirv_bytes_accepts_valid_module() {
+        let buf = spirv_bytes(&[SPIRV_MAGIC, 0x0001_0000, 0, 1, 0]);
+        assert_eq!(decode_spirv_bytes(&buf).unwrap(), vec![SPIRV_MAGIC, 0x0001_0000, 0, 1, 0]);
+    }
+
+    #[test]
+    fn decode_spirv_bytes_rejects_unaligned_buffer() {
+        let mut buf = spirv_bytes(&[SPIRV_MAGIC]);
+        buf.push(0);
+        assert!(decode_spirv_bytes(&buf).unwrap_err().contains("word-aligned"));
+    }
+
+    #[test]
+    fn decode_spirv_bytes_rejects_wrong_magic_number() {
+        let buf = spirv_bytes(&[0xdead_beef, 0, 0, 0]);
+        assert!(decode_spirv_bytes(&buf).unwrap_err().contains("magic number"));
+    }
+
+    #[test]
+    fn decode_spirv_bytes_rejects_empty_buffer() {
+        assert!(decode_spirv_bytes(&[]).unwrap_err().contains("magic number"));
+    }
+
+    #[test]
+    fn entry_points_cannot_combine_with_top_level_fields() {
+        assert!(check_entry_points_exclusivity(true, true, false).is_err());
+    }
+
+    #[test]
+    fn entry_points_cannot_be_empty() {
+        assert!(check_entry_points_exclusivity(true, false, true).is_err());
+    }
+
+    #[test]
+    fn entry_points_alone_is_fine() {
+        assert!(check_entry_points_exclusivity(true, false, false).is_ok());
+    }
+
+    #[test]
+    fn top_level_fields_alone_is_fine() {
+        assert!(check_entry_points_exclusivity(false, true, false).is_ok());
+    }
 }